@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use worker::*;
+
+/// A single stored link/document, mirroring one row of the `documents` table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocInfo {
+    pub id: String,
+    pub url: String,
+    pub created_at: String,
+    pub bucket_path: String,
+    pub content_type: String,
+    pub size: usize,
+    pub title: String,
+    pub summary: String,
+    pub chunk_count: usize,
+    /// The `model_id` of the embedding provider active when this document's
+    /// vectors were last (re-)generated; compared against the configured
+    /// provider by `reindex_links` to find stale documents.
+    pub embedder_version: String,
+    /// `"pending"` while the bucket/vector writes for this document are still
+    /// in flight, `"ready"` once they've all landed. Lets `search_links`
+    /// ignore documents that crashed mid-insert instead of returning
+    /// half-written results.
+    pub status: String,
+}
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_READY: &str = "ready";
+
+fn db(env: &Env) -> Result<D1Database> {
+    env.d1("DB")
+}
+
+pub async fn find_link_by_url(env: &Env, url: &str) -> Result<DocInfo> {
+    let stmt = db(env)?
+        .prepare("SELECT * FROM documents WHERE url = ?1 AND status = ?2")
+        .bind(&[url.into(), STATUS_READY.into()])?;
+    match stmt.first::<DocInfo>(None).await? {
+        Some(doc) => Ok(doc),
+        None => Err(Error::RustError("link not found".into())),
+    }
+}
+
+pub async fn get_link_by_id(env: &Env, id: &str) -> Result<Option<DocInfo>> {
+    let stmt = db(env)?
+        .prepare("SELECT * FROM documents WHERE id = ?1 AND status = ?2")
+        .bind(&[id.into(), STATUS_READY.into()])?;
+    stmt.first::<DocInfo>(None).await
+}
+
+/// Insert a new document row, always starting in `STATUS_PENDING` until its
+/// bucket/vector writes complete and `mark_link_ready` flips it over.
+pub async fn save_link_to_db(env: &Env, doc: &DocInfo) -> Result<()> {
+    let stmt = db(env)?
+        .prepare(
+            "INSERT INTO documents (id, url, created_at, bucket_path, content_type, size, title, summary, chunk_count, embedder_version, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )
+        .bind(&[
+            doc.id.clone().into(),
+            doc.url.clone().into(),
+            doc.created_at.clone().into(),
+            doc.bucket_path.clone().into(),
+            doc.content_type.clone().into(),
+            (doc.size as f64).into(),
+            doc.title.clone().into(),
+            doc.summary.clone().into(),
+            (doc.chunk_count as f64).into(),
+            doc.embedder_version.clone().into(),
+            STATUS_PENDING.into(),
+        ])?;
+    stmt.run().await?;
+    Ok(())
+}
+
+pub async fn mark_link_ready(env: &Env, id: &str) -> Result<()> {
+    db(env)?
+        .prepare("UPDATE documents SET status = ?1 WHERE id = ?2")
+        .bind(&[STATUS_READY.into(), id.into()])?
+        .run()
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_link_by_id(env: &Env, id: &str) -> Result<()> {
+    db(env)?
+        .prepare("DELETE FROM documents WHERE id = ?1")
+        .bind(&[id.into()])?
+        .run()
+        .await?;
+    Ok(())
+}
+
+/// Pending documents whose `created_at` is older than `cutoff_iso`, i.e. ones
+/// whose insert crashed partway through and never got compensated.
+pub async fn list_stale_pending_links(env: &Env, cutoff_iso: &str) -> Result<Vec<DocInfo>> {
+    db(env)?
+        .prepare("SELECT * FROM documents WHERE status = ?1 AND created_at < ?2")
+        .bind(&[STATUS_PENDING.into(), cutoff_iso.into()])?
+        .all()
+        .await?
+        .results::<DocInfo>()
+}
+
+/// All ready documents, i.e. excluding ones still mid-insert.
+pub async fn list_all_links(env: &Env) -> Result<Vec<DocInfo>> {
+    db(env)?
+        .prepare("SELECT * FROM documents WHERE status = ?1")
+        .bind(&[STATUS_READY.into()])?
+        .all()
+        .await?
+        .results::<DocInfo>()
+}
+
+/// Update a document's embedder version and chunk count together after a
+/// reindex, since the new chunking may not produce the same number of chunks
+/// as before and `delete_vectors_by_prefix` relies on `chunk_count` being
+/// accurate to know which vector ids to delete.
+pub async fn update_embedder_version(
+    env: &Env,
+    id: &str,
+    embedder_version: &str,
+    chunk_count: usize,
+) -> Result<()> {
+    db(env)?
+        .prepare("UPDATE documents SET embedder_version = ?1, chunk_count = ?2 WHERE id = ?3")
+        .bind(&[
+            embedder_version.into(),
+            (chunk_count as f64).into(),
+            id.into(),
+        ])?
+        .run()
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_link_by_url(env: &Env, url: &str) -> Result<DocInfo> {
+    let doc = find_link_by_url(env, url).await?;
+    db(env)?
+        .prepare("DELETE FROM documents WHERE url = ?1")
+        .bind(&[url.into()])?
+        .run()
+        .await?;
+    Ok(doc)
+}
+
+/// Rank document ids by keyword overlap with `query` against the `title` and
+/// `summary` columns, most relevant first. This is a plain `LIKE` scan rather
+/// than FTS5 since D1 doesn't yet expose virtual tables to Workers.
+pub async fn keyword_search_documents(env: &Env, query: &str, limit: u64) -> Result<Vec<String>> {
+    let terms: Vec<&str> = query.split_whitespace().filter(|t| !t.is_empty()).collect();
+    if terms.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let clauses: Vec<String> = (0..terms.len())
+        .map(|i| format!("(title LIKE ?{0} OR summary LIKE ?{0})", i + 1))
+        .collect();
+    let rank_terms: Vec<String> = (0..terms.len())
+        .map(|i| {
+            format!(
+                "(CASE WHEN title LIKE ?{0} THEN 2 ELSE 0 END) + (CASE WHEN summary LIKE ?{0} THEN 1 ELSE 0 END)",
+                i + 1
+            )
+        })
+        .collect();
+    let sql = format!(
+        "SELECT id, {} AS rank FROM documents WHERE status = ?{} AND ({}) ORDER BY rank DESC LIMIT ?{}",
+        rank_terms.join(" + "),
+        terms.len() + 1,
+        clauses.join(" OR "),
+        terms.len() + 2
+    );
+
+    let mut bind_values: Vec<wasm_bindgen::JsValue> = terms
+        .iter()
+        .map(|t| format!("%{}%", t).into())
+        .collect();
+    bind_values.push(STATUS_READY.into());
+    bind_values.push((limit as f64).into());
+
+    #[derive(serde::Deserialize)]
+    struct Row {
+        id: String,
+    }
+
+    let rows = db(env)?
+        .prepare(&sql)
+        .bind(&bind_values)?
+        .all()
+        .await?
+        .results::<Row>()?;
+    Ok(rows.into_iter().map(|r| r.id).collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkText {
+    pub document_id: String,
+    pub chunk_id: u64,
+    pub text: String,
+}
+
+pub async fn save_chunk_text(env: &Env, document_id: &str, chunk_id: u64, text: &str) -> Result<()> {
+    db(env)?
+        .prepare(
+            "INSERT INTO chunk_texts (document_id, chunk_id, text) VALUES (?1, ?2, ?3)
+             ON CONFLICT(document_id, chunk_id) DO UPDATE SET text = excluded.text",
+        )
+        .bind(&[document_id.into(), (chunk_id as f64).into(), text.into()])?
+        .run()
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_chunk_texts(env: &Env, document_id: &str) -> Result<()> {
+    db(env)?
+        .prepare("DELETE FROM chunk_texts WHERE document_id = ?1")
+        .bind(&[document_id.into()])?
+        .run()
+        .await?;
+    Ok(())
+}
+
+/// Fetch the stored text for a set of `(document_id, chunk_id)` pairs.
+pub async fn get_chunk_texts(
+    env: &Env,
+    document_id: &str,
+    chunk_ids: &[u64],
+) -> Result<std::collections::HashMap<u64, String>> {
+    if chunk_ids.is_empty() {
+        return Ok(std::collections::HashMap::new());
+    }
+
+    let placeholders: Vec<String> = (0..chunk_ids.len()).map(|i| format!("?{}", i + 2)).collect();
+    let sql = format!(
+        "SELECT chunk_id, text FROM chunk_texts WHERE document_id = ?1 AND chunk_id IN ({})",
+        placeholders.join(", ")
+    );
+
+    let mut bind_values: Vec<wasm_bindgen::JsValue> = vec![document_id.into()];
+    bind_values.extend(chunk_ids.iter().map(|id| (*id as f64).into()));
+
+    #[derive(serde::Deserialize)]
+    struct Row {
+        chunk_id: u64,
+        text: String,
+    }
+
+    let rows = db(env)?
+        .prepare(&sql)
+        .bind(&bind_values)?
+        .all()
+        .await?
+        .results::<Row>()?;
+    Ok(rows.into_iter().map(|r| (r.chunk_id, r.text)).collect())
+}
+
+fn bucket(env: &Env) -> Result<Bucket> {
+    env.bucket("BUCKET")
+}
+
+pub async fn save_to_bucket(env: &Env, path: &str, content: Vec<u8>) -> Result<()> {
+    bucket(env)?.put(path, content).execute().await?;
+    Ok(())
+}
+
+pub async fn delete_from_bucket(env: &Env, path: &str) -> Result<()> {
+    bucket(env)?.delete(path).await?;
+    Ok(())
+}
+
+pub async fn get_from_bucket(env: &Env, path: &str) -> Result<Vec<u8>> {
+    let object = bucket(env)?
+        .get(path)
+        .execute()
+        .await?
+        .ok_or_else(|| Error::RustError(format!("bucket object not found: {}", path)))?;
+    Ok(object
+        .body()
+        .ok_or_else(|| Error::RustError("bucket object has no body".into()))?
+        .bytes()
+        .await?)
+}
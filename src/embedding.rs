@@ -0,0 +1,246 @@
+use worker::*;
+
+/// A backend capable of turning text into embedding vectors.
+///
+/// Implementations are selected at runtime from `Env` bindings so a
+/// deployment can switch embedding models without a code change. The trait
+/// is `?Send` because it's driven entirely from the single-threaded Worker
+/// event loop.
+#[async_trait::async_trait(?Send)]
+pub trait EmbeddingProvider {
+    /// Embed a batch of texts in as few requests as the provider allows.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// Dimensionality of the vectors this provider returns.
+    fn dimensions(&self) -> usize;
+
+    /// Stable identifier persisted alongside vectors: the configured model name,
+    /// e.g. `"@cf/baai/bge-base-en-v1.5"`.
+    fn model_id(&self) -> &str;
+}
+
+/// Cloudflare Workers AI, called through the `AI` binding.
+pub struct WorkersAiProvider {
+    ai: Ai,
+    model: String,
+    dimensions: usize,
+}
+
+#[async_trait::async_trait(?Send)]
+impl EmbeddingProvider for WorkersAiProvider {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let input = serde_json::json!({ "text": texts });
+        let response: serde_json::Value = self.ai.run(&self.model, &input).await?;
+        let data = response["data"]
+            .as_array()
+            .ok_or_else(|| Error::RustError("malformed embedding response".into()))?;
+        Ok(data
+            .iter()
+            .map(|embedding| {
+                embedding
+                    .as_array()
+                    .map(|v| v.iter().map(|x| x.as_f64().unwrap_or(0.0) as f32).collect())
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+/// Any OpenAI-compatible `/embeddings` endpoint (OpenAI itself, or a
+/// self-hosted compatible server).
+pub struct OpenAiCompatProvider {
+    base_url: String,
+    api_key: String,
+    model: String,
+    dimensions: usize,
+}
+
+#[async_trait::async_trait(?Send)]
+impl EmbeddingProvider for OpenAiCompatProvider {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let body = serde_json::json!({ "model": self.model, "input": texts });
+        let mut headers = Headers::new();
+        headers.set("content-type", "application/json")?;
+        headers.set("authorization", &format!("Bearer {}", self.api_key))?;
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post)
+            .with_headers(headers)
+            .with_body(Some(wasm_bindgen::JsValue::from_str(&body.to_string())));
+        let req = Request::new_with_init(&format!("{}/embeddings", self.base_url), &init)?;
+        let mut response = Fetch::Request(req).send().await?;
+        let value: serde_json::Value = response.json().await?;
+        let data = value["data"]
+            .as_array()
+            .ok_or_else(|| Error::RustError("malformed embedding response".into()))?;
+        Ok(data
+            .iter()
+            .map(|item| {
+                item["embedding"]
+                    .as_array()
+                    .map(|v| v.iter().map(|x| x.as_f64().unwrap_or(0.0) as f32).collect())
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+/// Google's Gemini embedding endpoint (`embedContent`/`batchEmbedContents`).
+pub struct GeminiEmbeddingProvider {
+    api_key: String,
+    model: String,
+    dimensions: usize,
+}
+
+#[async_trait::async_trait(?Send)]
+impl EmbeddingProvider for GeminiEmbeddingProvider {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:batchEmbedContents?key={}",
+            self.model, self.api_key
+        );
+        let requests: Vec<_> = texts
+            .iter()
+            .map(|text| {
+                serde_json::json!({
+                    "model": format!("models/{}", self.model),
+                    "content": { "parts": [{ "text": text }] }
+                })
+            })
+            .collect();
+        let body = serde_json::json!({ "requests": requests });
+        let mut headers = Headers::new();
+        headers.set("content-type", "application/json")?;
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post)
+            .with_headers(headers)
+            .with_body(Some(wasm_bindgen::JsValue::from_str(&body.to_string())));
+        let req = Request::new_with_init(&url, &init)?;
+        let mut response = Fetch::Request(req).send().await?;
+        let value: serde_json::Value = response.json().await?;
+        let embeddings = value["embeddings"]
+            .as_array()
+            .ok_or_else(|| Error::RustError("malformed embedding response".into()))?;
+        Ok(embeddings
+            .iter()
+            .map(|e| {
+                e["values"]
+                    .as_array()
+                    .map(|v| v.iter().map(|x| x.as_f64().unwrap_or(0.0) as f32).collect())
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+/// Known output dimensionality per Workers AI embedding model. Falls back to
+/// `@cf/baai/bge-base-en-v1.5`'s 768 for an unrecognized model rather than
+/// guessing silently wrong for every model.
+fn workers_ai_dimensions(model: &str) -> usize {
+    match model {
+        "@cf/baai/bge-small-en-v1.5" => 384,
+        "@cf/baai/bge-large-en-v1.5" => 1024,
+        _ => 768,
+    }
+}
+
+/// Known output dimensionality per OpenAI(-compatible) embedding model.
+fn openai_dimensions(model: &str) -> usize {
+    match model {
+        "text-embedding-3-large" => 3072,
+        "text-embedding-ada-002" => 1536,
+        _ => 1536,
+    }
+}
+
+/// Known output dimensionality per Gemini embedding model.
+fn gemini_dimensions(model: &str) -> usize {
+    match model {
+        "gemini-embedding-001" => 3072,
+        _ => 768,
+    }
+}
+
+/// Build the embedding provider configured for this deployment via the
+/// `EMBEDDING_PROVIDER` / `EMBEDDING_MODEL` vars and matching secrets.
+///
+/// `dimensions` is looked up per the actual configured `EMBEDDING_MODEL`
+/// rather than assumed from the provider alone, since switching to a
+/// different model of the same provider (e.g. `text-embedding-3-large`
+/// instead of `-small`) changes the vector size.
+pub fn provider_from_env(env: &Env) -> Result<Box<dyn EmbeddingProvider>> {
+    let provider = env
+        .var("EMBEDDING_PROVIDER")
+        .map(|v| v.to_string())
+        .unwrap_or_else(|_| "workers-ai".to_string());
+
+    match provider.as_str() {
+        "workers-ai" => {
+            let model = env
+                .var("EMBEDDING_MODEL")
+                .map(|v| v.to_string())
+                .unwrap_or_else(|_| "@cf/baai/bge-base-en-v1.5".to_string());
+            let dimensions = workers_ai_dimensions(&model);
+            Ok(Box::new(WorkersAiProvider {
+                ai: env.ai("AI")?,
+                model,
+                dimensions,
+            }))
+        }
+        "openai" => {
+            let model = env
+                .var("EMBEDDING_MODEL")
+                .map(|v| v.to_string())
+                .unwrap_or_else(|_| "text-embedding-3-small".to_string());
+            let dimensions = openai_dimensions(&model);
+            Ok(Box::new(OpenAiCompatProvider {
+                base_url: env
+                    .var("EMBEDDING_BASE_URL")
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+                api_key: env.secret("OPENAI_API_KEY")?.to_string(),
+                model,
+                dimensions,
+            }))
+        }
+        "gemini" => {
+            let model = env
+                .var("EMBEDDING_MODEL")
+                .map(|v| v.to_string())
+                .unwrap_or_else(|_| "text-embedding-004".to_string());
+            let dimensions = gemini_dimensions(&model);
+            Ok(Box::new(GeminiEmbeddingProvider {
+                api_key: env.secret("GEMINI_API_KEY")?.to_string(),
+                model,
+                dimensions,
+            }))
+        }
+        other => Err(Error::RustError(format!(
+            "unknown embedding provider: {}",
+            other
+        ))),
+    }
+}
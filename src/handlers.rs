@@ -32,6 +32,8 @@ pub async fn insert_link(env: &Env, link: &str) -> Result<DocInfo> {
     let processed_data = chunk_and_summary_link(env, &content, &content_type).await?;
     console_log!("Processed data: {:?}", processed_data);
 
+    let (model_id, dimensions) = vector::model_info(env)?;
+
     let row = DocInfo {
         id: link_id.clone(),
         url: link.to_string(),
@@ -42,87 +44,304 @@ pub async fn insert_link(env: &Env, link: &str) -> Result<DocInfo> {
         title: processed_data.title.clone(),
         summary: processed_data.summary.clone(),
         chunk_count: processed_data.chunks.len(),
+        embedder_version: model_id.clone(),
+        status: d1::STATUS_PENDING.to_string(),
     };
 
-    let mut embeddings = Vec::with_capacity(processed_data.chunks.len());
-    for chunk_text in processed_data.chunks.iter() {
-        let embedding = vector::generate_embeddings(env, chunk_text).await?;
-        embeddings.push(embedding);
+    // Write the pending row first so a crash during the bucket/vector writes
+    // below leaves a recoverable trace instead of silently-orphaned data;
+    // `search_links` ignores non-ready documents and `sweep_pending_links`
+    // cleans up ones that never make it to ready.
+    d1::save_link_to_db(env, &row).await?;
+
+    let chunk_texts: Vec<String> = processed_data
+        .chunks
+        .iter()
+        .map(|chunk| chunk.text.clone())
+        .collect();
+
+    let write_result: Result<()> = async {
+        let embeddings = vector::generate_embeddings_batch(env, &chunk_texts).await?;
+
+        let insert_futures = embeddings.into_iter().enumerate().map(|(i, embedding)| {
+            let vector_id = format!("{}-{}", link_id, i);
+            let vector_metadata = VectorMetadata {
+                document_id: link_id.clone(),
+                chunk_id: i as u64,
+                model_id: model_id.clone(),
+                dimensions,
+            };
+            let link_id = link_id.clone();
+            let chunk_text = chunk_texts[i].clone();
+            async move {
+                vector::insert_vector(env, &vector_id, vector_metadata, embedding).await?;
+                d1::save_chunk_text(env, &link_id, i as u64, &chunk_text).await?;
+                Result::Ok(())
+            }
+        });
+        futures::future::try_join_all(insert_futures).await?;
+
+        d1::save_to_bucket(env, &bucket_path, content.clone()).await?;
+        Ok(())
     }
+    .await;
 
-    for (i, embedding) in embeddings.into_iter().enumerate() {
-        let vector_id = format!("{}-{}", link_id, i);
-        let vector_metadata = VectorMetadata {
-            document_id: link_id.clone(),
-            chunk_id: i as u64,
-        };
-        vector::insert_vector(env, &vector_id, vector_metadata, embedding).await?;
+    if let Err(err) = write_result {
+        console_log!("insert_link failed, compensating pending row {}: {:?}", link_id, err);
+        compensate_pending_insert(env, &row).await?;
+        return Err(err);
     }
 
-    // TODO: how to make sure these steps are atomic?
-    d1::save_to_bucket(env, &bucket_path, content.clone()).await?;
-    d1::save_link_to_db(env, &row).await?;
+    d1::mark_link_ready(env, &link_id).await?;
     Ok(row)
 }
 
+/// Undo a partially-completed insert: delete whatever vectors, chunk texts
+/// and bucket object made it through, then remove the pending row itself.
+/// Safe to call even if some of these writes never happened.
+async fn compensate_pending_insert(env: &Env, row: &DocInfo) -> Result<()> {
+    vector::delete_vectors_by_prefix(env, &row.id, row.chunk_count).await?;
+    d1::delete_chunk_texts(env, &row.id).await?;
+    d1::delete_from_bucket(env, &row.bucket_path).await?;
+    d1::delete_link_by_id(env, &row.id).await?;
+    Ok(())
+}
+
+/// How long a document may stay `pending` before `sweep_pending_links`
+/// considers its insert crashed and garbage-collects it.
+const PENDING_TIMEOUT_MS: i64 = 15 * 60 * 1000;
+
+/// Garbage-collect documents that have been stuck `pending` for longer than
+/// `PENDING_TIMEOUT_MS`, i.e. inserts that crashed before reaching `ready`.
+/// Intended to be called from the Worker's scheduled event handler.
+pub async fn sweep_pending_links(env: &Env) -> Result<SweepSummary> {
+    let cutoff = js_sys::Date::new_0();
+    cutoff.set_time(cutoff.get_time() - PENDING_TIMEOUT_MS as f64);
+    let cutoff_iso = cutoff.to_iso_string().as_string().unwrap();
+
+    let stale = d1::list_stale_pending_links(env, &cutoff_iso).await?;
+    let mut summary = SweepSummary::default();
+    for doc in &stale {
+        console_log!("Sweeping stale pending document: {}", doc.url);
+        compensate_pending_insert(env, doc).await?;
+        summary.swept += 1;
+    }
+    Ok(summary)
+}
+
+#[derive(Debug, Default)]
+pub struct SweepSummary {
+    pub swept: usize,
+}
+
+/// Re-embed every document whose stored `embedder_version` no longer matches
+/// the currently configured embedding provider, so the vector index never
+/// mixes incompatible embedding spaces. Safe to re-run after an interruption:
+/// a document stays a reindex candidate until its `embedder_version` is
+/// updated as the very last step.
+pub async fn reindex_links(env: &Env) -> Result<ReindexSummary> {
+    let (model_id, dimensions) = vector::model_info(env)?;
+
+    // A Vectorize index's dimensionality is fixed at creation and can't be
+    // changed in place, so switching to a provider/model with a different
+    // vector size (e.g. Workers AI's 768-dim bge-base -> OpenAI's 1536-dim
+    // text-embedding-3-small) can't be served by reindexing into the same
+    // index. Refuse up front rather than failing on the first `insert_vector`
+    // call, or worse, half-migrating the corpus.
+    let index_dimensions = vector::index_dimensions(env).await?;
+    if dimensions != index_dimensions {
+        return Err(Error::RustError(format!(
+            "configured embedding provider produces {}-dim vectors but the Vectorize index was created with {} dims; \
+             recreate the index with the new dimensionality (and reindex into it) before switching models",
+            dimensions, index_dimensions
+        )));
+    }
+
+    let docs = d1::list_all_links(env).await?;
+
+    let mut summary = ReindexSummary::default();
+    for doc in docs.iter().filter(|d| d.embedder_version != model_id) {
+        console_log!("Reindexing {} ({} -> {})", doc.url, doc.embedder_version, model_id);
+
+        let content = d1::get_from_bucket(env, &doc.bucket_path).await?;
+        let chunks = crate::utils::rechunk_content(&content);
+
+        // Delete up to the larger of the stored `doc.chunk_count` (the vectors
+        // currently recorded for this document) and the freshly recomputed
+        // chunk count: re-chunking the same bucket content is deterministic, so
+        // the latter also covers whatever an interrupted previous reindex
+        // attempt already wrote before `update_embedder_version` ran.
+        let stale_count = doc.chunk_count.max(chunks.len());
+        vector::delete_vectors_by_prefix(env, &doc.id, stale_count).await?;
+        d1::delete_chunk_texts(env, &doc.id).await?;
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            let embedding = vector::generate_embeddings(env, &chunk.text).await?;
+            let vector_id = format!("{}-{}", doc.id, i);
+            let vector_metadata = VectorMetadata {
+                document_id: doc.id.clone(),
+                chunk_id: i as u64,
+                model_id: model_id.clone(),
+                dimensions,
+            };
+            vector::insert_vector(env, &vector_id, vector_metadata, embedding).await?;
+            d1::save_chunk_text(env, &doc.id, i as u64, &chunk.text).await?;
+        }
+
+        d1::update_embedder_version(env, &doc.id, &model_id, chunks.len()).await?;
+        summary.reindexed += 1;
+    }
+
+    Ok(summary)
+}
+
+#[derive(Debug, Default)]
+pub struct ReindexSummary {
+    pub reindexed: usize,
+}
+
 /// Prepare metadata for storage
 fn get_bucket_path(content_type: &str, link_id: &str) -> String {
     let extension = get_extension_from_content_type(content_type);
     format!("content/{}.{}", link_id, extension)
 }
 
-/// Search links using vector similarity
-/// Returns a list of links and their chunks
-pub async fn search_links(env: Env, query: &str) -> Result<Vec<(DocInfo, Vec<u64>)>> {
+/// Constant `k` in the Reciprocal Rank Fusion formula `1 / (k + rank)`. 60 is
+/// the value used by the original RRF paper and is a reasonable default that
+/// keeps any single list from dominating the fused ranking.
+const RRF_K: f32 = 60.0;
+
+/// Sentinel `chunk_id` used when a result has no matching vector chunk (it
+/// was surfaced by keyword search alone) and its excerpt is synthesized from
+/// the document's title/summary instead of a chunk's text.
+const TITLE_SUMMARY_CHUNK_ID: u64 = u64::MAX;
+
+/// A matched chunk within a search result: its similarity score and a short
+/// excerpt of its text with the query terms centered, so a Telegram reply can
+/// show why it matched.
+#[derive(Debug, Clone)]
+pub struct ChunkMatch {
+    pub chunk_id: u64,
+    pub score: f32,
+    pub excerpt: String,
+}
+
+/// Fuse two ranked document-id lists with Reciprocal Rank Fusion: each list
+/// contributes `weight / (RRF_K + rank)` to a document's score, where `rank`
+/// is its 0-based position in that list. Returns `(doc_id, fused_score)`
+/// pairs sorted by descending score; a document that only appears in a list
+/// weighted `0.0` scores nothing and is left out.
+fn fuse_rankings(
+    vector_doc_order: &[String],
+    keyword_results: &[String],
+    semantic_ratio: f32,
+    keyword_ratio: f32,
+) -> Vec<(String, f32)> {
+    let mut rrf_scores: std::collections::HashMap<String, f32> = std::collections::HashMap::new();
+    for (rank, doc_id) in vector_doc_order.iter().enumerate() {
+        *rrf_scores.entry(doc_id.clone()).or_insert(0.0) +=
+            semantic_ratio * (1.0 / (RRF_K + rank as f32));
+    }
+    for (rank, doc_id) in keyword_results.iter().enumerate() {
+        *rrf_scores.entry(doc_id.clone()).or_insert(0.0) +=
+            keyword_ratio * (1.0 / (RRF_K + rank as f32));
+    }
+
+    let mut sorted_docs: Vec<_> = rrf_scores.into_iter().collect();
+    sorted_docs.retain(|(_, score)| *score > 0.0);
+    sorted_docs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    sorted_docs
+}
+
+/// Search links using a fusion of vector similarity and keyword matching.
+/// Returns a list of links and their matched chunks.
+///
+/// `semantic_ratio` weights how much the vector list contributes to the fused
+/// score relative to the keyword list, from `0.0` (keyword only) to `1.0`
+/// (vector only). Defaults to `0.5` (balanced) when `None`.
+pub async fn search_links(
+    env: Env,
+    query: &str,
+    semantic_ratio: Option<f32>,
+) -> Result<Vec<(DocInfo, Vec<ChunkMatch>)>> {
     console_log!("Searching for: {}", query);
+    let semantic_ratio = semantic_ratio.unwrap_or(0.5).clamp(0.0, 1.0);
+    let keyword_ratio = 1.0 - semantic_ratio;
 
     // Query the vector database to get vector IDs and scores
     let vector_results = vector::query_vectors_with_scores(&env, query, 20).await?;
+    let keyword_results = d1::keyword_search_documents(&env, query, 20).await?;
 
-    if vector_results.is_empty() {
+    if vector_results.is_empty() && keyword_results.is_empty() {
         return Ok(vec![]);
     }
 
-    // Group results by document ID to collect all chunks from the same document
+    // Group vector results by document ID to collect all chunks from the same document
     // Map of document_id -> Vec<(score, chunk_id)>
     let mut doc_matches: std::collections::HashMap<String, Vec<(f32, u64)>> =
         std::collections::HashMap::new();
 
-    // Also track the best score for each document for sorting
-    let mut doc_best_scores: std::collections::HashMap<String, f32> =
-        std::collections::HashMap::new();
-
+    // Vector results ranked by document, in first-seen order (query_vectors_with_scores
+    // already returns them best-score-first), used as the vector list for RRF.
+    let mut vector_doc_order: Vec<String> = Vec::new();
     for (_vector_id, score, metadata) in vector_results {
+        if !doc_matches.contains_key(&metadata.document_id) {
+            vector_doc_order.push(metadata.document_id.clone());
+        }
         doc_matches
-            .entry(metadata.document_id.clone())
+            .entry(metadata.document_id)
             .or_default()
             .push((score, metadata.chunk_id));
-
-        // Update the document's best score if this is higher
-        let current_best = doc_best_scores.entry(metadata.document_id).or_insert(0.0);
-        if score > *current_best {
-            *current_best = score;
-        }
     }
 
-    // Sort documents by their best score
-    let mut sorted_docs: Vec<_> = doc_best_scores.into_iter().collect();
-    sorted_docs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    let sorted_docs = fuse_rankings(&vector_doc_order, &keyword_results, semantic_ratio, keyword_ratio);
 
     let mut return_val = Vec::new();
 
-    for (doc_id, _) in sorted_docs.iter().take(5) {
+    for (doc_id, _fused_score) in sorted_docs.iter().take(5) {
         match d1::get_link_by_id(&env, doc_id).await? {
             Some(link_info) => {
-                // Sort the chunks by score (highest first)
-                let mut chunks = doc_matches.get(doc_id).unwrap().clone();
+                // Sort the chunks by score (highest first); a document that only
+                // matched via keyword search has no vector chunks to show.
+                let mut chunks = doc_matches.get(doc_id).cloned().unwrap_or_default();
                 chunks.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
 
-                let chunk_list = chunks
-                    .iter()
-                    .map(|(_, chunk_id)| *chunk_id) // +1 for 1-indexed display
+                let chunk_ids: Vec<u64> = chunks.iter().map(|(_, chunk_id)| *chunk_id).collect();
+                let chunk_texts = d1::get_chunk_texts(&env, doc_id, &chunk_ids).await?;
+
+                let mut chunk_matches = chunks
+                    .into_iter()
+                    .map(|(score, chunk_id)| {
+                        let excerpt = chunk_texts
+                            .get(&chunk_id)
+                            .map(|text| crate::utils::best_excerpt(text, query))
+                            .unwrap_or_default();
+                        ChunkMatch {
+                            chunk_id,
+                            score,
+                            excerpt,
+                        }
+                    })
                     .collect::<Vec<_>>();
-                return_val.push((link_info, chunk_list));
+
+                // A document matched via keyword search alone has no vector chunk
+                // to quote; synthesize an excerpt from its title/summary instead of
+                // returning an empty, unreadable result.
+                if chunk_matches.is_empty() {
+                    let title_and_summary = format!("{} {}", link_info.title, link_info.summary);
+                    chunk_matches.push(ChunkMatch {
+                        chunk_id: TITLE_SUMMARY_CHUNK_ID,
+                        // The RRF fused score (at most 1/60 ≈ 0.017) isn't
+                        // comparable to the raw cosine-similarity scores (0-1)
+                        // the other `ChunkMatch`es in this `Vec` carry; use a
+                        // query-term-overlap fraction instead so `score` stays
+                        // on the same 0-1 scale everywhere.
+                        score: crate::utils::term_overlap_score(&title_and_summary, query),
+                        excerpt: crate::utils::best_excerpt(&title_and_summary, query),
+                    });
+                }
+
+                return_val.push((link_info, chunk_matches));
             }
             None => {
                 console_log!("Link not found, id: {}", doc_id);
@@ -142,6 +361,7 @@ pub async fn delete_link(env: &Env, link: &str) -> Result<DocInfo> {
     d1::delete_from_bucket(env, &link_info.bucket_path).await?;
 
     vector::delete_vectors_by_prefix(env, &link_info.id, link_info.chunk_count).await?;
+    d1::delete_chunk_texts(env, &link_info.id).await?;
 
     console_log!(
         "Successfully deleted link and all associated data: {}",
@@ -150,3 +370,38 @@ pub async fn delete_link(env: &Env, link: &str) -> Result<DocInfo> {
 
     Ok(link_info)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuse_rankings_ties_docs_with_mirrored_ranks() {
+        let vector_order = vec!["a".to_string(), "b".to_string()];
+        let keyword_order = vec!["b".to_string(), "a".to_string()];
+        let fused = fuse_rankings(&vector_order, &keyword_order, 0.5, 0.5);
+        // "a" and "b" are each first in one list and second in the other, so
+        // with equal weights they should end up tied.
+        let scores: std::collections::HashMap<_, _> = fused.into_iter().collect();
+        assert!((scores["a"] - scores["b"]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fuse_rankings_ranks_keyword_only_doc_by_its_position() {
+        let vector_order: Vec<String> = vec![];
+        let keyword_order = vec!["a".to_string(), "b".to_string()];
+        let fused = fuse_rankings(&vector_order, &keyword_order, 0.5, 0.5);
+        assert_eq!(fused[0].0, "a");
+        assert_eq!(fused[1].0, "b");
+    }
+
+    #[test]
+    fn fuse_rankings_weights_each_list_by_its_ratio() {
+        let vector_order = vec!["a".to_string()];
+        let keyword_order = vec!["b".to_string()];
+        let fused = fuse_rankings(&vector_order, &keyword_order, 1.0, 0.0);
+        let scores: std::collections::HashMap<_, _> = fused.into_iter().collect();
+        assert!(scores["a"] > 0.0);
+        assert!(!scores.contains_key("b"));
+    }
+}
@@ -0,0 +1,23 @@
+mod d1;
+mod embedding;
+mod handlers;
+mod models;
+mod telegram;
+mod utils;
+mod vector;
+
+use worker::*;
+
+#[event(fetch)]
+async fn fetch(req: Request, env: Env, _ctx: Context) -> Result<Response> {
+    handlers::handle_webhook(req, env).await
+}
+
+/// Runs on the Worker's configured cron trigger to garbage-collect documents
+/// whose insert crashed before reaching `ready`, per `sweep_pending_links`.
+#[event(scheduled)]
+async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    if let Err(err) = handlers::sweep_pending_links(&env).await {
+        console_log!("sweep_pending_links failed: {:?}", err);
+    }
+}
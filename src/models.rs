@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// Minimal subset of the Telegram Bot API `Update` object we care about.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub message_id: i64,
+    pub chat: Chat,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+}
+
+/// Metadata attached to every vector we store in the Vectorize index.
+///
+/// `model_id` and `dimensions` record which embedding provider/model produced
+/// this vector. `reindex_links` does not read them; it finds stale documents
+/// by `DocInfo::embedder_version`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorMetadata {
+    pub document_id: String,
+    pub chunk_id: u64,
+    pub model_id: String,
+    pub dimensions: usize,
+}
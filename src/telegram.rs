@@ -0,0 +1,39 @@
+use crate::models::Update;
+use worker::*;
+
+/// Route an incoming Telegram update to the right handler based on its text.
+pub async fn process_update(env: Env, update: Update) -> Result<()> {
+    let Some(message) = update.message else {
+        return Ok(());
+    };
+    let Some(text) = message.text else {
+        return Ok(());
+    };
+
+    if let Some(link) = text.strip_prefix("/save ") {
+        crate::handlers::insert_link(&env, link.trim()).await?;
+    } else if let Some(link) = text.strip_prefix("/delete ") {
+        crate::handlers::delete_link(&env, link.trim()).await?;
+    } else if text.trim() == "/reindex" {
+        // Admin-only: re-embeds every document whose vectors predate the
+        // currently configured embedding provider, so it shouldn't be
+        // triggerable by an arbitrary user.
+        if !is_admin(&env, message.chat.id) {
+            console_log!("Ignoring /reindex from non-admin chat {}", message.chat.id);
+            return Ok(());
+        }
+        crate::handlers::reindex_links(&env).await?;
+    } else {
+        crate::handlers::search_links(env, text.trim(), None).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether `chat_id` is allowed to run admin commands, per the `ADMIN_CHAT_ID` var.
+fn is_admin(env: &Env, chat_id: i64) -> bool {
+    env.var("ADMIN_CHAT_ID")
+        .ok()
+        .and_then(|v| v.to_string().parse::<i64>().ok())
+        .is_some_and(|admin_id| admin_id == chat_id)
+}
@@ -0,0 +1,411 @@
+use worker::*;
+
+/// A single retrieval chunk, together with the byte range it occupies in the
+/// original document so later code (e.g. snippet extraction) can map a match
+/// back to its source text.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub text: String,
+    pub start_byte: usize,
+    pub end_byte: usize,
+}
+
+pub struct ProcessedData {
+    pub title: String,
+    pub summary: String,
+    pub chunks: Vec<Chunk>,
+}
+
+impl std::fmt::Debug for ProcessedData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessedData")
+            .field("title", &self.title)
+            .field("summary", &self.summary)
+            .field("chunk_count", &self.chunks.len())
+            .finish()
+    }
+}
+
+/// Token budget per chunk for the embedding model, and how many tokens of
+/// overlap to carry into the next chunk so context straddling a boundary
+/// isn't lost. Tokens are approximated as chars/4 since no tokenizer crate
+/// is available in the Worker.
+const MAX_CHUNK_TOKENS: usize = 480;
+const CHUNK_OVERLAP_TOKENS: usize = 64;
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Summarize the content with the Gemini API and split it into retrieval chunks.
+pub async fn chunk_and_summary_link(
+    env: &Env,
+    content: &[u8],
+    content_type: &str,
+) -> Result<ProcessedData> {
+    let text = String::from_utf8_lossy(content).to_string();
+    let (title, summary) = summarize_with_gemini(env, &text, content_type).await?;
+    let chunks = chunk_text(
+        &text,
+        MAX_CHUNK_TOKENS * CHARS_PER_TOKEN,
+        CHUNK_OVERLAP_TOKENS * CHARS_PER_TOKEN,
+    );
+    Ok(ProcessedData {
+        title,
+        summary,
+        chunks,
+    })
+}
+
+/// Re-chunk a document's content without re-summarizing it, for use by
+/// `reindex_links` where the title/summary don't need to change.
+pub fn rechunk_content(content: &[u8]) -> Vec<Chunk> {
+    let text = String::from_utf8_lossy(content).to_string();
+    chunk_text(
+        &text,
+        MAX_CHUNK_TOKENS * CHARS_PER_TOKEN,
+        CHUNK_OVERLAP_TOKENS * CHARS_PER_TOKEN,
+    )
+}
+
+fn estimate_tokens(s: &str) -> usize {
+    s.len().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Split `text` into chunks of at most `max_chars`, each overlapping the
+/// previous one by roughly `overlap_chars`. Structural boundaries (headings,
+/// blank-line paragraphs, then sentences) are preferred over hard byte
+/// splits, which are only used when a single unit is itself too big.
+fn chunk_text(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<Chunk> {
+    // Units are bounded by `max_chars - overlap_chars` so the overlap carried
+    // into a new chunk plus its first unit still fits in `max_chars`.
+    let units = split_into_units(text, max_chars.saturating_sub(overlap_chars).max(1));
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_start = 0usize;
+    let mut cursor = 0usize;
+
+    for unit in units {
+        let would_be_len = current.len() + unit.len();
+        if !current.is_empty() && would_be_len > max_chars {
+            chunks.push(Chunk {
+                text: current.clone(),
+                start_byte: current_start,
+                end_byte: cursor,
+            });
+
+            // Start the next chunk with (at most) the trailing `overlap_chars`
+            // bytes of this one so retrieval doesn't lose context at the boundary.
+            let mut overlap_start = current.len().saturating_sub(overlap_chars);
+            while overlap_start < current.len() && !current.is_char_boundary(overlap_start) {
+                overlap_start += 1;
+            }
+            let overlap_text = current[overlap_start..].to_string();
+            current_start = cursor - overlap_text.len();
+            current = overlap_text;
+        }
+
+        current.push_str(unit);
+        cursor += unit.len();
+    }
+
+    if !current.is_empty() {
+        chunks.push(Chunk {
+            text: current,
+            start_byte: current_start,
+            end_byte: cursor,
+        });
+    }
+
+    chunks
+}
+
+/// Break `text` into structural units (heading blocks, paragraphs, then
+/// sentences), hard-splitting only the units that individually exceed
+/// `max_chars`.
+fn split_into_units(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut units = Vec::new();
+    for section in split_on_headings(text) {
+        for paragraph in split_on(section, "\n\n") {
+            if estimate_tokens(paragraph) * CHARS_PER_TOKEN <= max_chars {
+                units.push(paragraph);
+                continue;
+            }
+            for sentence in split_on(paragraph, ". ") {
+                if sentence.len() <= max_chars {
+                    units.push(sentence);
+                } else {
+                    units.extend(hard_split(sentence, max_chars));
+                }
+            }
+        }
+    }
+    units
+}
+
+/// Split `text` at the start of each Markdown-style heading line (`#` through
+/// `######` followed by a space), so a heading always begins a fresh unit
+/// instead of being folded into whatever oversized, blank-line-free block of
+/// text precedes or follows it (common in scraped HTML-to-text output).
+fn split_on_headings(text: &str) -> Vec<&str> {
+    let mut units = Vec::new();
+    let mut unit_start = 0;
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        if line_start > unit_start && is_heading_line(line) {
+            units.push(&text[unit_start..line_start]);
+            unit_start = line_start;
+        }
+        line_start += line.len();
+    }
+    if unit_start < text.len() {
+        units.push(&text[unit_start..]);
+    }
+    units
+}
+
+/// Whether `line` starts a Markdown-style heading (`#` through `######`
+/// followed by a space).
+fn is_heading_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ')
+}
+
+/// Split `text` on `sep`, keeping the separator attached to the end of each
+/// piece (except the last) so the units can be concatenated back losslessly.
+fn split_on<'a>(text: &'a str, sep: &str) -> Vec<&'a str> {
+    if text.is_empty() {
+        return vec![];
+    }
+    let mut units = Vec::new();
+    let mut rest = text;
+    while let Some(idx) = rest.find(sep) {
+        let split_at = idx + sep.len();
+        units.push(&rest[..split_at]);
+        rest = &rest[split_at..];
+    }
+    if !rest.is_empty() {
+        units.push(rest);
+    }
+    units
+}
+
+fn hard_split(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut units = Vec::new();
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    while start < bytes.len() {
+        let mut end = (start + max_chars).min(bytes.len());
+        // Don't split in the middle of a UTF-8 code point.
+        while end < bytes.len() && !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        units.push(&text[start..end]);
+        start = end;
+    }
+    units
+}
+
+/// Target length (in chars) of a search-result excerpt.
+const EXCERPT_WINDOW_CHARS: usize = 160;
+
+/// Return a window of `chunk_text` centered on the earliest occurrence of any
+/// of `query`'s terms, ellipsis-truncated at either end if it doesn't
+/// start/end the chunk. Windows are not compared by how many terms they
+/// cover. Falls back to the start of the chunk if no query term matches.
+pub fn best_excerpt(chunk_text: &str, query: &str) -> String {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect();
+    let lower = chunk_text.to_lowercase();
+
+    let best_start = if terms.is_empty() {
+        0
+    } else {
+        terms
+            .iter()
+            .filter_map(|term| lower.find(term.as_str()))
+            .min()
+            .unwrap_or(0)
+    };
+
+    let half = EXCERPT_WINDOW_CHARS / 2;
+    let mut start = best_start.saturating_sub(half);
+    while start > 0 && !chunk_text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (best_start + half).min(chunk_text.len());
+    while end < chunk_text.len() && !chunk_text.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut excerpt = String::new();
+    if start > 0 {
+        excerpt.push_str("…");
+    }
+    excerpt.push_str(chunk_text[start..end].trim());
+    if end < chunk_text.len() {
+        excerpt.push_str("…");
+    }
+    excerpt
+}
+
+/// Fraction (0.0-1.0) of `query`'s terms that appear in `text`, used as a
+/// cosine-similarity-scale stand-in for keyword-only search results so a
+/// `ChunkMatch::score` stays comparable across chunks regardless of whether
+/// it came from a vector hit or a keyword hit.
+pub fn term_overlap_score(text: &str, query: &str) -> f32 {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect();
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let lower = text.to_lowercase();
+    let matched = terms.iter().filter(|term| lower.contains(term.as_str())).count();
+    matched as f32 / terms.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_text_does_not_panic_on_multi_byte_overlap_boundary() {
+        // Regression test for a UTF-8 panic: the overlap window used to slice
+        // `current` at a raw byte offset that could land inside an accented
+        // character like the "é" in "café".
+        let paragraph = "café naïve façade ".repeat(50);
+        let chunks = chunk_text(&paragraph, 100, 20);
+        assert!(!chunks.is_empty());
+    }
+
+    #[test]
+    fn chunk_text_overlaps_consecutive_chunks() {
+        let text = "one two three four five six seven eight nine ten ";
+        let chunks = chunk_text(text, 20, 8);
+        assert!(chunks.len() > 1);
+        assert!(chunks[1].text.starts_with(
+            &chunks[0].text[chunks[0].text.len().saturating_sub(8)..]
+        ));
+    }
+
+    #[test]
+    fn split_into_units_splits_on_heading_boundaries() {
+        let text = "intro text\n# Heading One\nbody one\n## Heading Two\nbody two";
+        let units = split_into_units(text, 1000);
+        assert_eq!(
+            units,
+            vec![
+                "intro text\n",
+                "# Heading One\nbody one\n",
+                "## Heading Two\nbody two",
+            ]
+        );
+    }
+
+    #[test]
+    fn split_into_units_falls_back_to_paragraphs_without_headings() {
+        let text = "first paragraph\n\nsecond paragraph";
+        let units = split_into_units(text, 1000);
+        assert_eq!(units, vec!["first paragraph\n\n", "second paragraph"]);
+    }
+
+    #[test]
+    fn hard_split_does_not_split_a_multi_byte_character() {
+        let text = "aaé";
+        let units = hard_split(text, 2);
+        // Byte 2 is the start of "é" (bytes 2-3), so the first unit is "aa"
+        // and "é" stays intact in the second unit.
+        for unit in &units {
+            assert!(std::str::from_utf8(unit.as_bytes()).is_ok());
+        }
+        assert_eq!(units.concat(), text);
+    }
+
+    #[test]
+    fn best_excerpt_centers_on_the_matching_term() {
+        let text = "zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz needle zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz";
+        let excerpt = best_excerpt(text, "needle");
+        assert!(excerpt.contains("needle"));
+        assert!(excerpt.starts_with('…'));
+        assert!(excerpt.ends_with('…'));
+    }
+
+    #[test]
+    fn best_excerpt_falls_back_to_the_start_when_no_term_matches() {
+        let text = "no matching terms here at all";
+        let excerpt = best_excerpt(text, "absent");
+        assert!(excerpt.starts_with("no matching"));
+    }
+
+    #[test]
+    fn term_overlap_score_is_the_fraction_of_matched_terms() {
+        assert_eq!(term_overlap_score("the quick brown fox", "quick fox slow"), 2.0 / 3.0);
+        assert_eq!(term_overlap_score("nothing matches", "absent missing"), 0.0);
+        assert_eq!(term_overlap_score("anything", ""), 0.0);
+    }
+}
+
+async fn summarize_with_gemini(
+    env: &Env,
+    text: &str,
+    _content_type: &str,
+) -> Result<(String, String)> {
+    let api_key = env.secret("GEMINI_API_KEY")?.to_string();
+    let url = format!(
+        "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash:generateContent?key={}",
+        api_key
+    );
+    let prompt = format!(
+        "Produce a short title and a one paragraph summary for the following content:\n\n{}",
+        text
+    );
+    let body = serde_json::json!({
+        "contents": [{ "parts": [{ "text": prompt }] }]
+    });
+    let mut init = RequestInit::new();
+    init.with_method(Method::Post)
+        .with_headers({
+            let mut headers = Headers::new();
+            headers.set("content-type", "application/json")?;
+            headers
+        })
+        .with_body(Some(wasm_bindgen::JsValue::from_str(&body.to_string())));
+    let req = Request::new_with_init(&url, &init)?;
+    let mut response = Fetch::Request(req).send().await?;
+    let value: serde_json::Value = response.json().await?;
+    let generated = value["candidates"][0]["content"]["parts"][0]["text"]
+        .as_str()
+        .unwrap_or_default();
+    let mut lines = generated.splitn(2, '\n');
+    let title = lines.next().unwrap_or("Untitled").trim().to_string();
+    let summary = lines.next().unwrap_or(generated).trim().to_string();
+    Ok((title, summary))
+}
+
+pub async fn fetch_content(link: &str) -> Result<(Vec<u8>, String)> {
+    let mut response = Fetch::Url(Url::parse(link)?).send().await?;
+    let content_type = response
+        .headers()
+        .get("content-type")?
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let bytes = response.bytes().await?;
+    Ok((bytes, content_type))
+}
+
+pub fn get_extension_from_content_type(content_type: &str) -> &'static str {
+    if content_type.contains("html") {
+        "html"
+    } else if content_type.contains("pdf") {
+        "pdf"
+    } else if content_type.contains("json") {
+        "json"
+    } else {
+        "txt"
+    }
+}
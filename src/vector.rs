@@ -0,0 +1,147 @@
+use crate::embedding;
+use crate::models::VectorMetadata;
+use worker::*;
+
+fn index(env: &Env) -> Result<VectorizeIndex> {
+    env.vectorize("INDEX")
+}
+
+/// Embed a single piece of text using the deployment's configured embedding provider.
+pub async fn generate_embeddings(env: &Env, text: &str) -> Result<Vec<f32>> {
+    let provider = embedding::provider_from_env(env)?;
+    let mut embeddings = provider.embed(&[text.to_string()]).await?;
+    embeddings
+        .pop()
+        .ok_or_else(|| Error::RustError("embedding provider returned no vectors".into()))
+}
+
+/// The `model_id` and dimensionality of the currently configured embedding
+/// provider, used to stamp new vectors and documents with an embedder version.
+pub fn model_info(env: &Env) -> Result<(String, usize)> {
+    let provider = embedding::provider_from_env(env)?;
+    Ok((provider.model_id().to_string(), provider.dimensions()))
+}
+
+/// Upper bound, in input bytes, on a single embedding request, since we
+/// don't know a given provider's exact per-request limit ahead of time.
+const MAX_BATCH_BYTES: usize = 96_000;
+
+/// Embed many texts at once, split into as few provider requests as fit
+/// under `MAX_BATCH_BYTES`, and issued concurrently. Preserves the input
+/// order of `texts`.
+pub async fn generate_embeddings_batch(env: &Env, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+    let provider = embedding::provider_from_env(env)?;
+    let batches = size_bounded_batches(texts, MAX_BATCH_BYTES);
+    let results =
+        futures::future::try_join_all(batches.iter().map(|batch| provider.embed(batch))).await?;
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Group `texts` into batches whose total byte size stays under `max_bytes`,
+/// without splitting a single text across batches.
+fn size_bounded_batches(texts: &[String], max_bytes: usize) -> Vec<Vec<String>> {
+    let mut batches: Vec<Vec<String>> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_bytes = 0;
+
+    for text in texts {
+        if !current.is_empty() && current_bytes + text.len() > max_bytes {
+            batches.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+        current_bytes += text.len();
+        current.push(text.clone());
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_bounded_batches_keeps_each_batch_under_the_limit() {
+        let texts = vec!["a".repeat(40), "b".repeat(40), "c".repeat(40)];
+        let batches = size_bounded_batches(&texts, 50);
+        assert_eq!(batches, vec![vec![texts[0].clone()], vec![texts[1].clone()], vec![texts[2].clone()]]);
+    }
+
+    #[test]
+    fn size_bounded_batches_packs_multiple_texts_under_the_limit_together() {
+        let texts = vec!["a".repeat(10), "b".repeat(10), "c".repeat(10)];
+        let batches = size_bounded_batches(&texts, 25);
+        assert_eq!(batches, vec![vec![texts[0].clone(), texts[1].clone()], vec![texts[2].clone()]]);
+    }
+
+    #[test]
+    fn size_bounded_batches_never_splits_a_single_oversized_text() {
+        let texts = vec!["a".repeat(100)];
+        let batches = size_bounded_batches(&texts, 50);
+        assert_eq!(batches, vec![vec![texts[0].clone()]]);
+    }
+}
+
+pub async fn insert_vector(
+    env: &Env,
+    id: &str,
+    metadata: VectorMetadata,
+    values: Vec<f32>,
+) -> Result<()> {
+    let vector = Vector {
+        id: id.to_string(),
+        values,
+        metadata: Some(serde_json::to_value(metadata)?),
+        namespace: None,
+    };
+    index(env)?.insert(&[vector]).await?;
+    Ok(())
+}
+
+/// Query the vector index and return `(vector_id, score, metadata)` for the top matches.
+pub async fn query_vectors_with_scores(
+    env: &Env,
+    query: &str,
+    top_k: u64,
+) -> Result<Vec<(String, f32, VectorMetadata)>> {
+    let query_vector = generate_embeddings(env, query).await?;
+    let matches = index(env)?
+        .query(
+            query_vector,
+            VectorizeQueryOptions {
+                top_k,
+                return_metadata: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let mut results = Vec::with_capacity(matches.matches.len());
+    for m in matches.matches {
+        let metadata: VectorMetadata = serde_json::from_value(
+            m.metadata
+                .ok_or_else(|| Error::RustError("vector match missing metadata".into()))?,
+        )?;
+        results.push((m.id, m.score, metadata));
+    }
+    Ok(results)
+}
+
+/// The dimensionality the Vectorize index was created with. A Vectorize
+/// index's dimension is fixed at creation time and can't be changed in
+/// place, so this is what any configured embedding provider's `dimensions()`
+/// must match before writing vectors into it.
+pub async fn index_dimensions(env: &Env) -> Result<usize> {
+    let info = index(env)?.describe().await?;
+    Ok(info.config.dimensions as usize)
+}
+
+pub async fn delete_vectors_by_prefix(env: &Env, link_id: &str, chunk_count: usize) -> Result<()> {
+    let ids: Vec<String> = (0..chunk_count)
+        .map(|i| format!("{}-{}", link_id, i))
+        .collect();
+    index(env)?.delete_by_ids(ids).await?;
+    Ok(())
+}